@@ -10,6 +10,8 @@
 
 //! Container traits
 
+use clone::Clone;
+use default::Default;
 use option::Option;
 
 pub trait Container {
@@ -25,6 +27,12 @@ pub trait Mutable: Container {
     fn clear(&mut self);
 }
 
+pub trait Retainable<T>: Mutable {
+    /// Remove every element for which `f` returns false, in a single
+    /// pass over the container.
+    fn retain(&mut self, f: &fn(&T) -> bool);
+}
+
 pub trait Map<K, V>: Mutable {
     /// Return true if the map contains a value for the specified key
     fn contains_key(&self, key: &K) -> bool;
@@ -63,6 +71,19 @@ pub trait Map<K, V>: Mutable {
     /// Return a mutable reference to the value corresponding to the key
     fn find_mut<'a>(&'a mut self, key: &K) -> Option<&'a mut V>;
 
+    /// Return a mutable reference to the value corresponding to the key,
+    /// inserting `default` and returning a reference to it if the key was
+    /// not already present. Implementors do this in a single traversal,
+    /// inserting only when the key is absent.
+    fn find_or_insert<'a>(&'a mut self, key: K, default: V) -> &'a mut V;
+
+    /// Return a mutable reference to the value corresponding to the key,
+    /// inserting the result of `f(&key)` and returning a reference to it if
+    /// the key was not already present. Implementors do this in a single
+    /// traversal, evaluating `f` only when the key is absent.
+    fn find_or_insert_with<'a>(&'a mut self, key: K, f: &fn(&K) -> V)
+        -> &'a mut V;
+
     /// Insert a key-value pair into the map. An existing value for a
     /// key is replaced by the new value. Return true if the key did
     /// not already exist in the map.
@@ -81,6 +102,46 @@ pub trait Map<K, V>: Mutable {
     fn pop(&mut self, k: &K) -> Option<V>;
 }
 
+pub trait SortedMap<K, V>: Map<K, V> {
+    /// Visit all keys and values in descending key order, stopping early
+    /// if the closure returns false
+    #[cfg(stage0)]
+    fn each_reverse<'a>(&'a self, f: &fn(&K, &'a V) -> bool);
+    /// Visit all keys and values in descending key order, stopping early
+    /// if the closure returns false
+    #[cfg(not(stage0))]
+    fn each_reverse<'a>(&'a self, f: &fn(&K, &'a V) -> bool) -> bool;
+
+    /// Visit all key-value pairs with keys `>= lower` in ascending key
+    /// order, stopping early if the closure returns false
+    #[cfg(stage0)]
+    fn each_from<'a>(&'a self, lower: &K, f: &fn(&K, &'a V) -> bool);
+    /// Visit all key-value pairs with keys `>= lower` in ascending key
+    /// order, stopping early if the closure returns false
+    #[cfg(not(stage0))]
+    fn each_from<'a>(&'a self, lower: &K, f: &fn(&K, &'a V) -> bool) -> bool;
+
+    /// Visit all key-value pairs with keys `< upper` in ascending key
+    /// order, stopping early if the closure returns false
+    #[cfg(stage0)]
+    fn each_to<'a>(&'a self, upper: &K, f: &fn(&K, &'a V) -> bool);
+    /// Visit all key-value pairs with keys `< upper` in ascending key
+    /// order, stopping early if the closure returns false
+    #[cfg(not(stage0))]
+    fn each_to<'a>(&'a self, upper: &K, f: &fn(&K, &'a V) -> bool) -> bool;
+
+    /// Visit all key-value pairs with keys in `[lower, upper)` in
+    /// ascending key order, stopping early if the closure returns false
+    #[cfg(stage0)]
+    fn each_in_range<'a>(&'a self, lower: &K, upper: &K,
+                         f: &fn(&K, &'a V) -> bool);
+    /// Visit all key-value pairs with keys in `[lower, upper)` in
+    /// ascending key order, stopping early if the closure returns false
+    #[cfg(not(stage0))]
+    fn each_in_range<'a>(&'a self, lower: &K, upper: &K,
+                         f: &fn(&K, &'a V) -> bool) -> bool;
+}
+
 #[cfg(stage0)]
 pub trait Set<T>: Mutable {
     /// Return true if the set contains a value
@@ -152,3 +213,263 @@ pub trait Set<T>: Mutable {
     /// Visit the values representing the union
     fn union(&self, other: &Self, f: &fn(&T) -> bool) -> bool;
 }
+
+pub trait SortedSet<T>: Set<T> {
+    /// Visit all values in descending order, stopping early if the
+    /// closure returns false
+    #[cfg(stage0)]
+    fn each_reverse(&self, f: &fn(&T) -> bool);
+    /// Visit all values in descending order, stopping early if the
+    /// closure returns false
+    #[cfg(not(stage0))]
+    fn each_reverse(&self, f: &fn(&T) -> bool) -> bool;
+}
+
+/// Value-returning counterparts to the `Set` visitor methods. Opt-in is
+/// deliberate: each implementor writes `impl SetAlgebra<T> for Foo {}` to
+/// pick up the default bodies, which keeps the methods overridable for
+/// speed. A blanket `impl<S: Set<T> + Default> SetAlgebra<T> for S` would
+/// make the whole API automatic but would then collide with any such
+/// specialized impl, so it is not used here.
+pub trait SetAlgebra<T: Clone>: Set<T> + Default {
+    /// Return a new set containing the union of `self` and `other`. The
+    /// default builds the result with the `union` visitor and `insert`;
+    /// specialized containers may override it for speed.
+    fn union_set(&self, other: &Self) -> Self {
+        let mut result: Self = Default::default();
+        self.union(other, |v| { result.insert(v.clone()); true });
+        result
+    }
+
+    /// Return a new set containing the intersection of `self` and `other`
+    fn intersection_set(&self, other: &Self) -> Self {
+        let mut result: Self = Default::default();
+        self.intersection(other, |v| { result.insert(v.clone()); true });
+        result
+    }
+
+    /// Return a new set containing the difference of `self` and `other`
+    fn difference_set(&self, other: &Self) -> Self {
+        let mut result: Self = Default::default();
+        self.difference(other, |v| { result.insert(v.clone()); true });
+        result
+    }
+
+    /// Return a new set containing the symmetric difference of `self` and
+    /// `other`
+    fn symmetric_difference_set(&self, other: &Self) -> Self {
+        let mut result: Self = Default::default();
+        self.symmetric_difference(other, |v| { result.insert(v.clone()); true });
+        result
+    }
+}
+
+/// Generate an integer-backed `Set<uint>` whose elements are the bit
+/// positions `0..::core::uint::bits`. The newtype wraps a single `uint` and
+/// carries no allocation, so it is a good fit for small dense sets of
+/// machine integers. It is built in the spirit of the `bitflags!` macro:
+///
+/// ```rust
+/// use core::container::{Container, Mutable, Set, SortedSet};
+///
+/// bitset!(Flags);
+///
+/// let mut f = Flags(0);
+/// f.insert(1);
+/// f.insert(4);
+/// assert!(f.contains(&1));
+/// assert!(f.len() == 2);
+///
+/// f.remove(&1);
+/// assert!(!f.contains(&1));
+/// assert!(f.len() == 1);
+///
+/// // visit the remaining bits from the highest downward
+/// f.each_reverse(|bit| { println(bit.to_str()); true });
+/// ```
+#[macro_export]
+macro_rules! bitset(
+    ($name:ident) => (
+        pub struct $name(uint);
+
+        impl Container for $name {
+            fn len(&const self) -> uint {
+                let $name(bits) = *self;
+                let mut bits = bits;
+                let mut n = 0;
+                while bits != 0 {
+                    n += bits & 1;
+                    bits >>= 1;
+                }
+                n
+            }
+
+            fn is_empty(&const self) -> bool {
+                let $name(bits) = *self;
+                bits == 0
+            }
+        }
+
+        impl Mutable for $name {
+            fn clear(&mut self) { *self = $name(0); }
+        }
+
+        impl Set<uint> for $name {
+            fn contains(&self, value: &uint) -> bool {
+                let $name(bits) = *self;
+                *value < ::core::uint::bits && (bits >> *value) & 1 != 0
+            }
+
+            fn insert(&mut self, value: uint) -> bool {
+                assert!(value < ::core::uint::bits);
+                let present = self.contains(&value);
+                let $name(bits) = *self;
+                *self = $name(bits | (1 << value));
+                !present
+            }
+
+            fn remove(&mut self, value: &uint) -> bool {
+                assert!(*value < ::core::uint::bits);
+                let present = self.contains(value);
+                let $name(bits) = *self;
+                *self = $name(bits & !(1 << *value));
+                present
+            }
+
+            fn is_disjoint(&self, other: &$name) -> bool {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                a & b == 0
+            }
+
+            fn is_subset(&self, other: &$name) -> bool {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                a & b == a
+            }
+
+            fn is_superset(&self, other: &$name) -> bool {
+                other.is_subset(self)
+            }
+
+            #[cfg(stage0)]
+            fn difference(&self, other: &$name, f: &fn(&uint) -> bool) {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                let mask = a & !b;
+                let mut i = 0;
+                while i < ::core::uint::bits {
+                    if (mask >> i) & 1 != 0 && !f(&i) { return; }
+                    i += 1;
+                }
+            }
+            #[cfg(not(stage0))]
+            fn difference(&self, other: &$name, f: &fn(&uint) -> bool) -> bool {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                let mask = a & !b;
+                let mut i = 0;
+                while i < ::core::uint::bits {
+                    if (mask >> i) & 1 != 0 && !f(&i) { return false; }
+                    i += 1;
+                }
+                true
+            }
+
+            #[cfg(stage0)]
+            fn symmetric_difference(&self, other: &$name, f: &fn(&uint) -> bool) {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                let mask = a ^ b;
+                let mut i = 0;
+                while i < ::core::uint::bits {
+                    if (mask >> i) & 1 != 0 && !f(&i) { return; }
+                    i += 1;
+                }
+            }
+            #[cfg(not(stage0))]
+            fn symmetric_difference(&self, other: &$name,
+                                    f: &fn(&uint) -> bool) -> bool {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                let mask = a ^ b;
+                let mut i = 0;
+                while i < ::core::uint::bits {
+                    if (mask >> i) & 1 != 0 && !f(&i) { return false; }
+                    i += 1;
+                }
+                true
+            }
+
+            #[cfg(stage0)]
+            fn intersection(&self, other: &$name, f: &fn(&uint) -> bool) {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                let mask = a & b;
+                let mut i = 0;
+                while i < ::core::uint::bits {
+                    if (mask >> i) & 1 != 0 && !f(&i) { return; }
+                    i += 1;
+                }
+            }
+            #[cfg(not(stage0))]
+            fn intersection(&self, other: &$name, f: &fn(&uint) -> bool) -> bool {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                let mask = a & b;
+                let mut i = 0;
+                while i < ::core::uint::bits {
+                    if (mask >> i) & 1 != 0 && !f(&i) { return false; }
+                    i += 1;
+                }
+                true
+            }
+
+            #[cfg(stage0)]
+            fn union(&self, other: &$name, f: &fn(&uint) -> bool) {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                let mask = a | b;
+                let mut i = 0;
+                while i < ::core::uint::bits {
+                    if (mask >> i) & 1 != 0 && !f(&i) { return; }
+                    i += 1;
+                }
+            }
+            #[cfg(not(stage0))]
+            fn union(&self, other: &$name, f: &fn(&uint) -> bool) -> bool {
+                let $name(a) = *self;
+                let $name(b) = *other;
+                let mask = a | b;
+                let mut i = 0;
+                while i < ::core::uint::bits {
+                    if (mask >> i) & 1 != 0 && !f(&i) { return false; }
+                    i += 1;
+                }
+                true
+            }
+        }
+
+        impl SortedSet<uint> for $name {
+            #[cfg(stage0)]
+            fn each_reverse(&self, f: &fn(&uint) -> bool) {
+                let $name(bits) = *self;
+                let mut i = ::core::uint::bits;
+                while i > 0 {
+                    i -= 1;
+                    if (bits >> i) & 1 != 0 && !f(&i) { return; }
+                }
+            }
+            #[cfg(not(stage0))]
+            fn each_reverse(&self, f: &fn(&uint) -> bool) -> bool {
+                let $name(bits) = *self;
+                let mut i = ::core::uint::bits;
+                while i > 0 {
+                    i -= 1;
+                    if (bits >> i) & 1 != 0 && !f(&i) { return false; }
+                }
+                true
+            }
+        }
+    )
+)